@@ -21,6 +21,11 @@ type File struct {
 	// ID is the encore.dev app id for the app.
 	// It is empty if the app is not linked to encore.dev.
 	ID string `json:"id"` // can be empty
+
+	// AllowMethodOverride specifies whether the app accepts the
+	// X-HTTP-Method-Override header on POST requests,
+	// for clients that can only send GET and POST requests.
+	AllowMethodOverride bool `json:"allow_method_override"`
 }
 
 // Parse parses the app file data into a File.
@@ -32,7 +32,9 @@ func (r *Run) ServeHTTP(w http.ResponseWriter, req *http.Request) {
 
 // forwardReq forwards the request to the Encore app.
 func (p *Proc) forwardReq(endpoint string, w http.ResponseWriter, req *http.Request) {
-	if req.Method == "OPTIONS" {
+	// Respond to CORS preflight requests directly, and forward
+	// other OPTIONS requests to the app so it can report the allowed methods.
+	if req.Method == "OPTIONS" && req.Header.Get("Access-Control-Request-Method") != "" {
 		w.Header().Set("Access-Control-Allow-Origin", "*")
 		w.Header().Set("Access-Control-Allow-Methods", "*")
 		w.Header().Set("Access-Control-Allow-Headers", "Content-Type, Authorization")
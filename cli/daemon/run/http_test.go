@@ -0,0 +1,53 @@
+package run
+
+import (
+	"net"
+	"net/http"
+	"net/http/httptest"
+	"testing"
+
+	qt "github.com/frankban/quicktest"
+	"github.com/hashicorp/yamux"
+)
+
+// TestForwardReqOptions tests that CORS preflight requests are answered
+// by the daemon, while other OPTIONS requests are forwarded to the app.
+func TestForwardReqOptions(t *testing.T) {
+	c := qt.New(t)
+
+	// Serve the app over yamux, as the runtime does.
+	clientConn, serverConn := net.Pipe()
+	srvSess, err := yamux.Server(serverConn, yamux.DefaultConfig())
+	c.Assert(err, qt.IsNil)
+	defer srvSess.Close()
+	cliSess, err := yamux.Client(clientConn, yamux.DefaultConfig())
+	c.Assert(err, qt.IsNil)
+	defer cliSess.Close()
+
+	forwarded := make(chan string, 1)
+	go http.Serve(srvSess, http.HandlerFunc(func(w http.ResponseWriter, req *http.Request) {
+		forwarded <- req.Method + " " + req.URL.Path
+		w.Header().Set("Allow", "GET, HEAD, OPTIONS")
+		w.WriteHeader(http.StatusNoContent)
+	}))
+
+	p := &Proc{Run: &Run{ListenAddr: "app"}, client: cliSess}
+
+	// A CORS preflight request is answered directly.
+	req := httptest.NewRequest("OPTIONS", "/svc.Endpoint", nil)
+	req.Header.Set("Origin", "http://example.com")
+	req.Header.Set("Access-Control-Request-Method", "POST")
+	w := httptest.NewRecorder()
+	p.forwardReq("svc.Endpoint", w, req)
+	c.Assert(w.Code, qt.Equals, http.StatusOK)
+	c.Assert(w.Header().Get("Access-Control-Allow-Origin"), qt.Equals, "*")
+	c.Assert(forwarded, qt.HasLen, 0)
+
+	// A plain OPTIONS request is forwarded to the app.
+	req = httptest.NewRequest("OPTIONS", "/svc.Endpoint", nil)
+	w = httptest.NewRecorder()
+	p.forwardReq("svc.Endpoint", w, req)
+	c.Assert(w.Code, qt.Equals, http.StatusNoContent)
+	c.Assert(w.Header().Get("Allow"), qt.Equals, "GET, HEAD, OPTIONS")
+	c.Assert(<-forwarded, qt.Equals, "OPTIONS /svc.Endpoint")
+}
@@ -252,13 +252,21 @@ func (r *Run) buildAndStart(ctx context.Context, parse *parser.Result) (p *Proc,
 		return nil, err
 	}
 
+	// Parse the app file on every build so changes to it
+	// take effect when the app is reloaded.
+	appFile, err := appfile.ParseFile(filepath.Join(r.Root, appfile.Name))
+	if err != nil {
+		return nil, err
+	}
+
 	cfg := &compiler.Config{
-		Version:           "",
-		WorkingDir:        r.params.WorkingDir,
-		CgoEnabled:        true,
-		EncoreRuntimePath: env.EncoreRuntimePath(),
-		EncoreGoRoot:      env.EncoreGoRoot(),
-		Parse:             parse,
+		Version:             "",
+		WorkingDir:          r.params.WorkingDir,
+		CgoEnabled:          true,
+		EncoreRuntimePath:   env.EncoreRuntimePath(),
+		EncoreGoRoot:        env.EncoreGoRoot(),
+		Parse:               parse,
+		AllowMethodOverride: appFile.AllowMethodOverride,
 	}
 
 	build, err := compiler.Build(r.Root, cfg)
@@ -45,6 +45,10 @@ func (s *server) RecordTrace(w http.ResponseWriter, req *http.Request) {
 		http.Error(w, "invalid X-Encore-Trace-ID header: "+err.Error(), http.StatusBadRequest)
 		return
 	}
+	if _, err := trace.ParseVersion(req.Header.Get("X-Encore-Trace-Version")); err != nil {
+		http.Error(w, "invalid X-Encore-Trace-Version header: "+err.Error(), http.StatusBadRequest)
+		return
+	}
 
 	proc := s.runMgr.FindProc(pid)
 	if proc == nil {
@@ -58,7 +62,7 @@ func (s *server) RecordTrace(w http.ResponseWriter, req *http.Request) {
 		return
 	}
 
-	reqs, err := trace.Parse(traceID, data, proc)
+	reqs, _, err := trace.Parse(traceID, data, proc, trace.ParseOptions{})
 	if err != nil {
 		log.Error().Err(err).Msg("runtime: could not parse trace")
 		http.Error(w, "could not parse trace: "+err.Error(), http.StatusBadRequest)
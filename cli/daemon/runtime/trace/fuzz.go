@@ -0,0 +1,26 @@
+// +build gofuzz
+
+package trace
+
+// Fuzz implements a fuzz test for the trace parser.
+//
+// To run:
+//
+//     $ go install github.com/dvyukov/go-fuzz/go-fuzz@latest github.com/dvyukov/go-fuzz/go-fuzz-build@latest
+//     $ cd cli/daemon/runtime/trace
+//     $ go get -d github.com/dvyukov/go-fuzz/go-fuzz-dep
+//     $ go-fuzz-build
+//     $ go-fuzz -bin=trace-fuzz.zip -workdir=./testdata
+//
+// go-fuzz-build requires the go-fuzz-dep package to be importable
+// from this module, which is what the "go get -d" step is for.
+// It adds go-fuzz to go.mod and go.sum; don't commit those changes.
+//
+// Stack traces are read but not symbolized, since there is no process.
+func Fuzz(data []byte) int {
+	reqs, _, err := Parse(ID{}, data, nil, ParseOptions{Lenient: true})
+	if err != nil || len(reqs) == 0 {
+		return 0
+	}
+	return 1
+}
@@ -3,6 +3,7 @@ package trace
 import (
 	"context"
 	"encoding/binary"
+	"errors"
 	"fmt"
 	"math"
 	"path/filepath"
@@ -19,6 +20,31 @@ import (
 
 type ID [16]byte
 
+// Version is a trace protocol version,
+// as sent by the runtime in the X-Encore-Trace-Version header.
+type Version string
+
+const (
+	// V3 is the trace protocol version used by the current runtime.
+	V3 Version = "v3"
+)
+
+// supportedVersions are the trace protocol versions the parser understands.
+var supportedVersions = []Version{V3}
+
+// ParseVersion parses a trace protocol version.
+// It reports an error if the version is not supported by the parser.
+func ParseVersion(s string) (Version, error) {
+	names := make([]string, len(supportedVersions))
+	for i, v := range supportedVersions {
+		if string(v) == s {
+			return v, nil
+		}
+		names[i] = string(v)
+	}
+	return "", fmt.Errorf("unsupported trace version %q (supported versions: %s)", s, strings.Join(names, ", "))
+}
+
 type TraceMeta struct {
 	ID      ID
 	Reqs    []*tracepb.Request
@@ -75,26 +101,47 @@ func (st *Store) List(appID string) []*TraceMeta {
 	return tr
 }
 
-func Parse(traceID ID, data []byte, proc *run.Proc) ([]*tracepb.Request, error) {
+// SkippedEvent describes a trace event that could not be parsed
+// and was skipped.
+type SkippedEvent struct {
+	Offset int    // offset of the event header in the trace data
+	Event  byte   // event type
+	Reason string // why the event was skipped
+}
+
+// ParseOptions are options for parsing traces.
+type ParseOptions struct {
+	// Lenient specifies whether to skip events that cannot be parsed
+	// instead of failing to parse the trace.
+	Lenient bool
+}
+
+// Parse parses the trace data.
+//
+// By default it reports an error if any event cannot be parsed.
+// If opts.Lenient is set it instead reports the events that were skipped,
+// in which case the returned requests describe a partial trace.
+func Parse(traceID ID, data []byte, proc *run.Proc, opts ParseOptions) (reqs []*tracepb.Request, skipped []SkippedEvent, err error) {
 	id := &tracepb.TraceID{
 		Low:  bin.Uint64(traceID[:8]),
 		High: bin.Uint64(traceID[8:]),
 	}
 	tp := &traceParser{
-		traceReader: traceReader{buf: data},
-		proc:        proc,
-		traceID:     id,
-		reqMap:      make(map[uint64]*tracepb.Request),
-		txMap:       make(map[uint64]*tracepb.DBTransaction),
-		queryMap:    make(map[uint64]*tracepb.DBQuery),
-		callMap:     make(map[uint64]interface{}),
-		goMap:       make(map[goKey]*tracepb.Goroutine),
-		httpMap:     make(map[uint64]*tracepb.HTTPCall),
+		data:     traceReader{buf: data},
+		lenient:  opts.Lenient,
+		proc:     proc,
+		traceID:  id,
+		reqMap:   make(map[uint64]*tracepb.Request),
+		txMap:    make(map[uint64]*tracepb.DBTransaction),
+		queryMap: make(map[uint64]*tracepb.DBQuery),
+		callMap:  make(map[uint64]interface{}),
+		goMap:    make(map[goKey]*tracepb.Goroutine),
+		httpMap:  make(map[uint64]*tracepb.HTTPCall),
 	}
 	if err := tp.Parse(); err != nil {
-		return nil, err
+		return nil, nil, err
 	}
-	return tp.reqs, nil
+	return tp.reqs, tp.skipped, nil
 }
 
 type goKey struct {
@@ -102,8 +149,13 @@ type goKey struct {
 	goid   uint32
 }
 
+// traceParser parses trace data. The embedded traceReader
+// reads the event currently being parsed, while data reads the whole trace.
 type traceParser struct {
 	traceReader
+	data    traceReader
+	lenient bool // skip events that cannot be parsed
+
 	proc     *run.Proc
 	traceID  *tracepb.TraceID
 	reqs     []*tracepb.Request
@@ -113,14 +165,46 @@ type traceParser struct {
 	callMap  map[uint64]interface{} // *RPCCall or *AuthCall
 	httpMap  map[uint64]*tracepb.HTTPCall
 	goMap    map[goKey]*tracepb.Goroutine
+	skipped  []SkippedEvent
 }
 
+// errFrameOverflow is reported for events whose data
+// extends beyond the event's frame.
+var errFrameOverflow = errors.New("invalid trace format: event exceeds frame size")
+
+// Parse parses the trace events.
+//
+// Each event is decoded from a reader limited to the event's frame,
+// so a malformed event cannot affect the parsing of the events following it.
+//
+// In lenient mode, events that fail to parse are recorded in tp.skipped
+// and do not modify the parser state. If an event's size exceeds the
+// remaining data, the size cannot be trusted: parsing resumes at the next
+// plausible event header (see resync), or stops if there is none,
+// keeping the events parsed thus far.
 func (tp *traceParser) Parse() error {
-	for i := 0; !tp.Done(); i++ {
-		ev := tp.Byte()
-		ts := tp.Uint64()
-		size := int(tp.Uint32())
-		startOff := tp.Offset()
+	for i := 0; !tp.data.Done(); i++ {
+		hdrOff := tp.data.Offset()
+		ev := tp.data.Byte()
+		ts := tp.data.Uint64()
+		size := int(tp.data.Uint32())
+		if tp.data.Overflow() || size > tp.data.Remaining() {
+			if !tp.lenient {
+				return fmt.Errorf("event #%d (offset %d): invalid trace format (truncated event %x)", i, hdrOff, ev)
+			}
+			next, ok := tp.resync(hdrOff + 1)
+			if !ok {
+				tp.skip(hdrOff, ev, "truncated event")
+				return nil
+			}
+			tp.skip(hdrOff, ev, fmt.Sprintf("invalid event size %d, resynchronized at offset %d", size, next))
+			tp.data = traceReader{buf: tp.data.buf, off: next}
+			continue
+		}
+
+		startOff := tp.data.Offset()
+		tp.traceReader = traceReader{buf: tp.data.buf[startOff : startOff+size]}
+		tp.data.Skip(size)
 
 		var err error
 		switch ev {
@@ -148,7 +232,7 @@ func (tp *traceParser) Parse() error {
 			err = tp.callEnd(ts)
 		case 0x0C, 0x0D:
 			// Skip these events for now
-			tp.Skip(size)
+			continue
 
 		case 0x0E:
 			err = tp.httpStart(ts)
@@ -161,26 +245,62 @@ func (tp *traceParser) Parse() error {
 
 		default:
 			log.Error().Int("idx", i).Hex("event", []byte{ev}).Msg("trace: unknown event type, skipping")
-			tp.Skip(size)
-			err = nil
+			continue
 		}
-		if err != nil {
-			return fmt.Errorf("event #%d: parsing event=%x: %v", i, ev, err)
+		if err == nil && tp.Overflow() {
+			err = errFrameOverflow
 		}
 
-		if tp.Overflow() {
-			return fmt.Errorf("event #%d: invalid trace format (reader overflow parsing event code %d)", i, ev)
-		} else if off, want := tp.Offset(), startOff+size; off < want {
-			log.Error().Int("idx", i).Hex("event", []byte{ev}).Int("remainingBytes", want-off).Msg("trace: parser did not consume whole frame, skipping ahead")
-			tp.Skip(want - off)
-		} else if off > want {
-			return fmt.Errorf("event #%d: parser (event=%x) exceeded frame size by %d bytes", i, ev, off-want)
+		if err != nil {
+			if !tp.lenient {
+				return fmt.Errorf("event #%d (offset %d): parsing event=%x: %v", i, hdrOff, ev, err)
+			}
+			tp.skip(hdrOff, ev, err.Error())
+		} else if !tp.Done() {
+			log.Error().Int("idx", i).Hex("event", []byte{ev}).Int("remainingBytes", tp.Remaining()).Msg("trace: parser did not consume whole frame, skipping ahead")
 		}
 	}
 
 	return nil
 }
 
+// skip records that the event at off was skipped.
+func (tp *traceParser) skip(off int, ev byte, reason string) {
+	tp.skipped = append(tp.skipped, SkippedEvent{Offset: off, Event: ev, Reason: reason})
+}
+
+// headerSize is the size of an event header:
+// the event type, the timestamp and the size of the event data.
+const headerSize = 1 + 8 + 4
+
+// resync returns the offset of the first plausible event header at or after off.
+// A header is plausible if it has a known event type, its data fits within
+// the trace, and it is followed either by the end of the trace or by
+// another known event type. It reports false if there is none.
+func (tp *traceParser) resync(off int) (int, bool) {
+	buf := tp.data.buf
+	for ; off+headerSize <= len(buf); off++ {
+		if !knownEvent(buf[off]) {
+			continue
+		}
+		size := int(bin.Uint32(buf[off+9 : off+headerSize]))
+		end := off + headerSize + size
+		if end == len(buf) || (end < len(buf) && knownEvent(buf[end])) {
+			return off, true
+		}
+	}
+	return 0, false
+}
+
+// knownEvent reports whether ev is a known event type.
+func knownEvent(ev byte) bool {
+	return ev >= 0x01 && ev <= 0x11
+}
+
+// The event parsers below read the whole event before modifying
+// the parser state, and return errFrameOverflow without modifying it
+// if the event data is incomplete.
+
 func (tp *traceParser) requestStart(ts uint64) error {
 	var typ tracepb.Request_Type
 	switch b := tp.Byte(); b {
@@ -205,15 +325,21 @@ func (tp *traceParser) requestStart(ts uint64) error {
 		Type:    typ,
 	}
 
-	for n, i := tp.UVarint(), uint64(0); i < n; i++ {
+	for n, i := tp.UVarint(), uint64(0); i < n && !tp.Overflow(); i++ {
 		size := tp.UVarint()
 		if size > (10 << 20) {
 			return fmt.Errorf("input too large: %d bytes", size)
+		} else if size > uint64(tp.Remaining()) {
+			// Don't allocate more than we could possibly read.
+			return errFrameOverflow
 		}
 		input := make([]byte, size)
 		tp.Bytes(input)
 		req.Inputs = append(req.Inputs, input)
 	}
+	if tp.Overflow() {
+		return errFrameOverflow
+	}
 	tp.reqs = append(tp.reqs, req)
 	tp.reqMap[req.SpanId] = req
 	return nil
@@ -225,27 +351,43 @@ func (tp *traceParser) requestEnd(ts uint64) error {
 	if !ok {
 		return fmt.Errorf("unknown request span: %v", spanID)
 	}
-	// dur := ts - rd.startTs
-	req.EndTime = ts
 
+	var (
+		outputs  [][]byte
+		errMsg   []byte
+		errStack *tracepb.StackTrace
+	)
 	if tp.Byte() == 0 {
 		// No error
-		for n, i := tp.UVarint(), uint64(0); i < n; i++ {
+		for n, i := tp.UVarint(), uint64(0); i < n && !tp.Overflow(); i++ {
 			size := tp.UVarint()
 			if size > (10 << 20) {
 				return fmt.Errorf("input too large: %d bytes", size)
+			} else if size > uint64(tp.Remaining()) {
+				// Don't allocate more than we could possibly read.
+				return errFrameOverflow
 			}
 			output := make([]byte, size)
 			tp.Bytes(output)
-			req.Outputs = append(req.Outputs, output)
+			outputs = append(outputs, output)
 		}
 	} else {
-		msg := tp.ByteString()
-		if len(msg) == 0 {
-			msg = []byte("unknown error")
+		errMsg = tp.ByteString()
+		if len(errMsg) == 0 {
+			errMsg = []byte("unknown error")
 		}
-		req.Err = msg
-		req.ErrStack = tp.stack(filterNone)
+		errStack = tp.stack(filterNone)
+	}
+	if tp.Overflow() {
+		return errFrameOverflow
+	}
+
+	// dur := ts - rd.startTs
+	req.EndTime = ts
+	req.Outputs = append(req.Outputs, outputs...)
+	if errMsg != nil {
+		req.Err = errMsg
+		req.ErrStack = errStack
 	}
 	return nil
 }
@@ -257,6 +399,9 @@ func (tp *traceParser) goroutineStart(ts uint64) error {
 		return fmt.Errorf("unknown request span id: %v", spanID)
 	}
 	goid := tp.Uint32()
+	if tp.Overflow() {
+		return errFrameOverflow
+	}
 	g := &tracepb.Goroutine{
 		Goid:      goid,
 		CallLoc:   0, // not yet supported
@@ -273,6 +418,9 @@ func (tp *traceParser) goroutineStart(ts uint64) error {
 func (tp *traceParser) goroutineEnd(ts uint64) error {
 	spanID := tp.Uint64()
 	goid := tp.Uint32()
+	if tp.Overflow() {
+		return errFrameOverflow
+	}
 	k := goKey{spanID: spanID, goid: goid}
 	g, ok := tp.goMap[k]
 	if !ok {
@@ -286,6 +434,9 @@ func (tp *traceParser) goroutineEnd(ts uint64) error {
 func (tp *traceParser) goroutineClear(ts uint64) error {
 	spanID := tp.Uint64()
 	goid := tp.Uint32()
+	if tp.Overflow() {
+		return errFrameOverflow
+	}
 	k := goKey{spanID: spanID, goid: goid}
 	g, ok := tp.goMap[k]
 	if !ok {
@@ -310,6 +461,9 @@ func (tp *traceParser) transactionStart(ts uint64) error {
 		StartTime:  ts,
 		BeginStack: tp.stack(filterDB),
 	}
+	if tp.Overflow() {
+		return errFrameOverflow
+	}
 	tp.txMap[txid] = tx
 	req.Events = append(req.Events, &tracepb.Event{
 		Data: &tracepb.Event_Tx{Tx: tx},
@@ -328,6 +482,19 @@ func (tp *traceParser) transactionEnd(ts uint64) error {
 	compl := tp.Byte()
 	errMsg := tp.ByteString()
 	stack := tp.stack(filterDB)
+	if tp.Overflow() {
+		return errFrameOverflow
+	}
+
+	var completion tracepb.DBTransaction_CompletionType
+	switch compl {
+	case 0:
+		completion = tracepb.DBTransaction_ROLLBACK
+	case 1:
+		completion = tracepb.DBTransaction_COMMIT
+	default:
+		return fmt.Errorf("unknown completion type: %x", compl)
+	}
 
 	// It's possible to get multiple transaction end events.
 	// Ignore them for now; we will expose this information later.
@@ -336,14 +503,7 @@ func (tp *traceParser) transactionEnd(ts uint64) error {
 		tx.EndLoc = 0 // TODO(eandre) reintroduce
 		tx.Err = errMsg
 		tx.EndStack = stack
-		switch compl {
-		case 0:
-			tx.Completion = tracepb.DBTransaction_ROLLBACK
-		case 1:
-			tx.Completion = tracepb.DBTransaction_COMMIT
-		default:
-			return fmt.Errorf("unknown completion type: %x", compl)
-		}
+		tx.Completion = completion
 	}
 	return nil
 }
@@ -364,13 +524,19 @@ func (tp *traceParser) queryStart(ts uint64) error {
 		Query:     tp.ByteString(),
 		Stack:     tp.stack(filterDB),
 	}
-	tp.queryMap[qid] = q
+	if tp.Overflow() {
+		return errFrameOverflow
+	}
 
+	var tx *tracepb.DBTransaction
 	if txid != 0 {
-		tx, ok := tp.txMap[txid]
-		if !ok {
+		if tx, ok = tp.txMap[txid]; !ok {
 			return fmt.Errorf("unknown transaction id: %v", txid)
 		}
+	}
+	tp.queryMap[qid] = q
+
+	if tx != nil {
 		tx.Queries = append(tx.Queries, q)
 	} else {
 		req.Events = append(req.Events, &tracepb.Event{
@@ -383,12 +549,16 @@ func (tp *traceParser) queryStart(ts uint64) error {
 
 func (tp *traceParser) queryEnd(ts uint64) error {
 	qid := tp.UVarint()
+	errMsg := tp.ByteString()
+	if tp.Overflow() {
+		return errFrameOverflow
+	}
 	q, ok := tp.queryMap[qid]
 	if !ok {
 		return fmt.Errorf("unknown query id: %v", qid)
 	}
 	q.EndTime = ts
-	q.Err = tp.ByteString()
+	q.Err = errMsg
 	return nil
 }
 
@@ -408,6 +578,9 @@ func (tp *traceParser) callStart(ts uint64) error {
 		Stack:     tp.stack(filterNone),
 		StartTime: ts,
 	}
+	if tp.Overflow() {
+		return errFrameOverflow
+	}
 	tp.callMap[callID] = c
 	req.Events = append(req.Events, &tracepb.Event{
 		Data: &tracepb.Event_Rpc{Rpc: c},
@@ -418,6 +591,9 @@ func (tp *traceParser) callStart(ts uint64) error {
 func (tp *traceParser) callEnd(ts uint64) error {
 	callID := tp.UVarint()
 	errMsg := tp.ByteString()
+	if tp.Overflow() {
+		return errFrameOverflow
+	}
 	c, ok := tp.callMap[callID].(*tracepb.RPCCall)
 	if !ok {
 		return fmt.Errorf("unknown call: %v ", callID)
@@ -443,6 +619,9 @@ func (tp *traceParser) httpStart(ts uint64) error {
 		Url:       tp.String(),
 		StartTime: ts,
 	}
+	if tp.Overflow() {
+		return errFrameOverflow
+	}
 	tp.httpMap[callID] = c
 	req.Events = append(req.Events, &tracepb.Event{
 		Data: &tracepb.Event_Http{Http: c},
@@ -458,26 +637,36 @@ func (tp *traceParser) httpEnd(ts uint64) error {
 	if !ok {
 		return fmt.Errorf("unknown call: %v ", callID)
 	}
-	c.EndTime = ts
-	c.Err = errMsg
-	c.StatusCode = uint32(status)
 
 	numEvents := tp.UVarint()
-	c.Events = make([]*tracepb.HTTPTraceEvent, 0, numEvents)
+	if numEvents > uint64(tp.Remaining()) {
+		return fmt.Errorf("too many http events: %d", numEvents)
+	}
+	events := make([]*tracepb.HTTPTraceEvent, 0, numEvents)
 	for i := 0; i < int(numEvents); i++ {
 		ev, err := tp.httpEvent()
 		if err != nil {
 			return err
 		}
-		c.Events = append(c.Events, ev)
+		events = append(events, ev)
+	}
+	if tp.Overflow() {
+		return errFrameOverflow
 	}
 
+	c.EndTime = ts
+	c.Err = errMsg
+	c.StatusCode = uint32(status)
+	c.Events = events
 	return nil
 }
 
 func (tp *traceParser) httpBodyClosed(ts uint64) error {
 	callID := tp.UVarint()
 	_ = tp.ByteString() // close error
+	if tp.Overflow() {
+		return errFrameOverflow
+	}
 	c, ok := tp.httpMap[callID]
 	if !ok {
 		return fmt.Errorf("unknown call: %v ", callID)
@@ -534,7 +723,7 @@ func (tp *traceParser) httpEvent() (*tracepb.HTTPTraceEvent, error) {
 			Err: tp.ByteString(),
 		}
 		addrs := int(tp.UVarint())
-		for j := 0; j < addrs; j++ {
+		for j := 0; j < addrs && !tp.Overflow(); j++ {
 			data.Addrs = append(data.Addrs, &tracepb.DNSAddr{
 				Ip: tp.ByteString(),
 			})
@@ -629,6 +818,9 @@ func (tp *traceParser) logMessage(ts uint64) error {
 		log.Fields = append(log.Fields, f)
 	}
 	log.Stack = tp.stack(filterNone)
+	if tp.Overflow() {
+		return errFrameOverflow
+	}
 
 	req.Events = append(req.Events, &tracepb.Event{
 		Data: &tracepb.Event_Log{Log: log},
@@ -698,24 +890,31 @@ func (tp *traceParser) stack(filterMode stackFilter) *tracepb.StackTrace {
 		return tr
 	}
 
-	sym, err := tp.proc.SymTable(context.Background())
-	if err != nil {
-		log.Error().Err(err).Msg("could not parse sym table")
-		return tr
-	}
-
-	pcs := make([]uint64, n)
+	// Read the pcs before symbolizing them, so the frame
+	// is fully consumed even if symbolization fails.
+	pcs := make([]int64, n)
 	prev := int64(0)
 	for i := 0; i < n; i++ {
 		diff := tp.Varint()
 		x := prev + diff
 		prev = x
-		pcs[i] = uint64(x) + sym.BaseOffset
+		pcs[i] = x
+	}
+
+	if tp.proc == nil {
+		// No process to symbolize the stack with (e.g. when fuzzing).
+		return tr
+	}
+	sym, err := tp.proc.SymTable(context.Background())
+	if err != nil {
+		log.Error().Err(err).Msg("could not parse sym table")
+		return tr
 	}
 
 	tr.Frames = make([]*tracepb.StackFrame, 0, n)
 PCLoop:
-	for _, pc := range pcs {
+	for _, x := range pcs {
+		pc := uint64(x) + sym.BaseOffset
 		file, line, fn := sym.PCToLine(pc)
 		if fn != nil {
 			if filterMode == filterDB && strings.Contains(filepath.ToSlash(file), "/src/database/sql/") {
@@ -751,6 +950,11 @@ func (tr *traceReader) Overflow() bool {
 	return tr.err
 }
 
+// Remaining reports the number of unread bytes.
+func (tr *traceReader) Remaining() int {
+	return len(tr.buf) - tr.off
+}
+
 func (tr *traceReader) Bytes(b []byte) {
 	n := copy(b, tr.buf[tr.off:])
 	tr.off += n
@@ -783,6 +987,12 @@ func (tr *traceReader) String() string {
 
 func (tr *traceReader) ByteString() []byte {
 	size := tr.UVarint()
+	if size > uint64(tr.Remaining()) {
+		// Don't allocate more than we could possibly read.
+		tr.off = len(tr.buf)
+		tr.err = true
+		return nil
+	}
 	b := make([]byte, int(size))
 	tr.Bytes(b)
 	return b
@@ -846,9 +1056,11 @@ func (tr *traceReader) UVarint() uint64 {
 		u |= uint64(b&^0x80) << i
 		tr.off++
 		if b&0x80 == 0 {
-			break
+			return u
 		}
 	}
+	// We ran out of data before the end of the varint.
+	tr.err = true
 	return u
 }
 
@@ -0,0 +1,241 @@
+package trace
+
+import (
+	"fmt"
+	"testing"
+
+	tracepb "encr.dev/proto/encore/engine/trace"
+	qt "github.com/frankban/quicktest"
+)
+
+// requestStartEvent returns the payload for a request start event.
+func requestStartEvent(spanID uint64) *eventBuf {
+	eb := &eventBuf{}
+	eb.Byte(0x01) // RPC call
+	eb.Uint64(spanID)
+	eb.Uint64(0)  // parent span id
+	eb.UVarint(0) // goid
+	eb.UVarint(0) // call loc
+	eb.UVarint(0) // def loc
+	eb.String("") // uid
+	eb.UVarint(0) // number of inputs
+	return eb
+}
+
+func TestParseSkipsCorruptEvents(t *testing.T) {
+	c := qt.New(t)
+
+	var (
+		w    traceWriter
+		want []SkippedEvent // expected skipped events, ignoring the reason
+	)
+	corrupt := func(ev byte, payload *eventBuf) {
+		want = append(want, SkippedEvent{Offset: len(w.Bytes()), Event: ev})
+		w.Event(ev, 1, payload)
+	}
+
+	w.Event(0x01, 0, requestStartEvent(1))
+	// Unknown request type.
+	corrupt(0x01, (&eventBuf{}).Byte(0xFF))
+	// Request end for an unknown span.
+	corrupt(0x02, (&eventBuf{}).Bytes([]byte{1, 2}))
+	// Request start that is cut short, and must not be recorded.
+	corrupt(0x01, &eventBuf{buf: requestStartEvent(5).buf[:17]})
+	// Request end for span 1 with an output exceeding the frame,
+	// which must not end the request.
+	corrupt(0x02, (&eventBuf{}).Uint64(1).Byte(0).UVarint(1).UVarint(10).Bytes([]byte("ab")))
+	w.Event(0x01, 0, requestStartEvent(2))
+	want = append(want, SkippedEvent{Offset: len(w.Bytes()), Event: 0x01})
+	w.Event(0x01, 0, requestStartEvent(3))
+	data := w.Bytes()
+	data = data[:len(data)-10] // truncate the last event
+
+	reqs, skipped, err := Parse(ID{}, data, nil, ParseOptions{Lenient: true})
+	c.Assert(err, qt.IsNil)
+	c.Assert(reqs, qt.HasLen, 2)
+	c.Assert(reqs[0].SpanId, qt.Equals, uint64(1))
+	c.Assert(reqs[0].EndTime, qt.Equals, uint64(0))
+	c.Assert(reqs[0].Outputs, qt.HasLen, 0)
+	c.Assert(reqs[1].SpanId, qt.Equals, uint64(2))
+
+	c.Assert(skipped, qt.HasLen, len(want))
+	for i, ev := range skipped {
+		c.Assert(ev.Offset, qt.Equals, want[i].Offset, qt.Commentf("skipped event #%d", i))
+		c.Assert(ev.Event, qt.Equals, want[i].Event, qt.Commentf("skipped event #%d", i))
+		c.Assert(ev.Reason, qt.Not(qt.Equals), "", qt.Commentf("skipped event #%d", i))
+	}
+	c.Assert(skipped[len(skipped)-1].Reason, qt.Equals, "truncated event")
+}
+
+func TestParseStrict(t *testing.T) {
+	c := qt.New(t)
+
+	var w traceWriter
+	w.Event(0x01, 0, requestStartEvent(1))
+	// Unknown request type.
+	w.Event(0x01, 0, (&eventBuf{}).Byte(0xFF))
+	w.Event(0x01, 0, requestStartEvent(2))
+
+	_, _, err := Parse(ID{}, w.Bytes(), nil, ParseOptions{})
+	c.Assert(err, qt.ErrorMatches, `event #1 \(offset 35\): parsing event=1: unknown request type ff`)
+
+	// Truncated events are reported as well.
+	w = traceWriter{}
+	w.Event(0x01, 0, requestStartEvent(1))
+	data := w.Bytes()
+	_, _, err = Parse(ID{}, data[:len(data)-1], nil, ParseOptions{})
+	c.Assert(err, qt.ErrorMatches, `event #0 \(offset 0\): invalid trace format \(truncated event 1\)`)
+}
+
+func TestParseResync(t *testing.T) {
+	c := qt.New(t)
+
+	var w traceWriter
+	w.Event(0x01, 0, requestStartEvent(1))
+	corruptOff := len(w.Bytes())
+	w.Event(0x01, 0, requestStartEvent(4))
+	w.Event(0x01, 0, requestStartEvent(2))
+	w.Event(0x01, 0, requestStartEvent(3))
+	data := w.Bytes()
+
+	// Corrupt the size of the second event so it exceeds the trace.
+	bin.PutUint32(data[corruptOff+9:corruptOff+13], 0xFFFFFF)
+
+	reqs, skipped, err := Parse(ID{}, data, nil, ParseOptions{Lenient: true})
+	c.Assert(err, qt.IsNil)
+	c.Assert(reqs, qt.HasLen, 3)
+	c.Assert(reqs[0].SpanId, qt.Equals, uint64(1))
+	c.Assert(reqs[1].SpanId, qt.Equals, uint64(2))
+	c.Assert(reqs[2].SpanId, qt.Equals, uint64(3))
+
+	nextOff := corruptOff + headerSize + len(requestStartEvent(4).buf)
+	c.Assert(skipped, qt.DeepEquals, []SkippedEvent{{
+		Offset: corruptOff,
+		Event:  0x01,
+		Reason: fmt.Sprintf("invalid event size %d, resynchronized at offset %d", 0xFFFFFF, nextOff),
+	}})
+}
+
+func TestParseMalformedEvents(t *testing.T) {
+	// httpStart is the payload for an HTTP call start event for call 1 in span 1.
+	httpStart := (&eventBuf{}).UVarint(1).Uint64(1).Uint64(2).UVarint(0).String("GET").String("http://example.com")
+
+	tests := []struct {
+		name   string
+		events func(w *traceWriter)
+		skip   string // expected reason for skipping the last event, or "" if none
+		check  func(c *qt.C, reqs []*tracepb.Request)
+	}{
+		{
+			name: "huge_bytestring",
+			events: func(w *traceWriter) {
+				w.Event(0x01, 0, requestStartEvent(1))
+				// Log message with a message length far exceeding the data.
+				w.Event(0x11, 1, (&eventBuf{}).Uint64(1).UVarint(0).Byte(1).UVarint(1 << 40).Bytes([]byte("msg")))
+			},
+			skip: errFrameOverflow.Error(),
+			check: func(c *qt.C, reqs []*tracepb.Request) {
+				c.Assert(reqs[0].Events, qt.HasLen, 0)
+			},
+		},
+		{
+			name: "huge_input",
+			events: func(w *traceWriter) {
+				eb := requestStartEvent(1)
+				eb.buf = eb.buf[:len(eb.buf)-1] // drop the number of inputs
+				eb.UVarint(1).UVarint(5 << 20).Bytes([]byte("ab"))
+				w.Event(0x01, 0, eb)
+			},
+			skip: errFrameOverflow.Error(),
+		},
+		{
+			name: "huge_output",
+			events: func(w *traceWriter) {
+				w.Event(0x01, 0, requestStartEvent(1))
+				w.Event(0x02, 1, (&eventBuf{}).Uint64(1).Byte(0).UVarint(1).UVarint(5 << 20).Bytes([]byte("ab")))
+			},
+			skip: errFrameOverflow.Error(),
+			check: func(c *qt.C, reqs []*tracepb.Request) {
+				c.Assert(reqs[0].EndTime, qt.Equals, uint64(0))
+			},
+		},
+		{
+			name: "truncated_varint",
+			events: func(w *traceWriter) {
+				// Request start ending in the middle of the goid varint.
+				w.Event(0x01, 0, (&eventBuf{}).Byte(0x01).Uint64(1).Uint64(0).Bytes([]byte{0x80, 0x80}))
+			},
+			skip: errFrameOverflow.Error(),
+		},
+		{
+			name: "huge_http_event_count",
+			events: func(w *traceWriter) {
+				w.Event(0x01, 0, requestStartEvent(1))
+				w.Event(0x0E, 1, httpStart)
+				w.Event(0x0F, 2, (&eventBuf{}).UVarint(1).String("").UVarint(200).UVarint(1 << 40))
+			},
+			skip: "too many http events: 1099511627776",
+			check: func(c *qt.C, reqs []*tracepb.Request) {
+				c.Assert(reqs[0].Events, qt.HasLen, 1)
+				call := reqs[0].Events[0].GetHttp()
+				c.Assert(call.EndTime, qt.Equals, uint64(0))
+				c.Assert(call.StatusCode, qt.Equals, uint32(0))
+			},
+		},
+		{
+			name: "stack_without_proc",
+			events: func(w *traceWriter) {
+				w.Event(0x01, 0, requestStartEvent(1))
+				eb := (&eventBuf{}).Uint64(1).UVarint(0).Byte(1).String("msg").UVarint(2)
+				// Error field with a stack trace, which cannot be symbolized
+				// but must still be consumed to parse the following field.
+				eb.Byte(1).String("err").String("boom")
+				eb.Byte(3).Varint(100).Varint(20).Varint(-10)
+				eb.Byte(2).String("key").String("value")
+				eb.Byte(0) // empty message stack
+				w.Event(0x11, 1, eb)
+			},
+			check: func(c *qt.C, reqs []*tracepb.Request) {
+				c.Assert(reqs[0].Events, qt.HasLen, 1)
+				msg := reqs[0].Events[0].GetLog()
+				c.Assert(msg.Fields, qt.HasLen, 2)
+				c.Assert(msg.Fields[0].GetError().Error, qt.Equals, "boom")
+				c.Assert(msg.Fields[0].GetError().Stack.Frames, qt.HasLen, 0)
+				c.Assert(msg.Fields[1].GetStr(), qt.Equals, "value")
+			},
+		},
+	}
+
+	for _, test := range tests {
+		test := test
+		t.Run(test.name, func(t *testing.T) {
+			c := qt.New(t)
+			var w traceWriter
+			test.events(&w)
+			reqs, skipped, err := Parse(ID{}, w.Bytes(), nil, ParseOptions{Lenient: true})
+			c.Assert(err, qt.IsNil)
+			if test.skip == "" {
+				c.Assert(skipped, qt.HasLen, 0)
+			} else {
+				c.Assert(skipped, qt.HasLen, 1)
+				c.Assert(skipped[0].Reason, qt.Equals, test.skip)
+			}
+			if test.check != nil {
+				test.check(c, reqs)
+			} else {
+				c.Assert(reqs, qt.HasLen, 0)
+			}
+		})
+	}
+}
+
+func TestParseVersion(t *testing.T) {
+	c := qt.New(t)
+
+	v, err := ParseVersion("v3")
+	c.Assert(err, qt.IsNil)
+	c.Assert(v, qt.Equals, V3)
+
+	_, err = ParseVersion("v2")
+	c.Assert(err, qt.ErrorMatches, `unsupported trace version "v2" \(supported versions: v3\)`)
+}
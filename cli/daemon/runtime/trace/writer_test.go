@@ -0,0 +1,78 @@
+package trace
+
+import (
+	"encoding/binary"
+)
+
+// traceWriter writes trace events in the binary format
+// the runtime produces, for building test fixtures.
+type traceWriter struct {
+	buf []byte
+}
+
+// Event writes an event of type ev with the given timestamp and payload.
+func (w *traceWriter) Event(ev byte, ts uint64, payload *eventBuf) {
+	var hdr [13]byte
+	hdr[0] = ev
+	bin.PutUint64(hdr[1:9], ts)
+	bin.PutUint32(hdr[9:13], uint32(len(payload.buf)))
+	w.buf = append(w.buf, hdr[:]...)
+	w.buf = append(w.buf, payload.buf...)
+}
+
+// Bytes returns the trace data written so far.
+func (w *traceWriter) Bytes() []byte {
+	return w.buf
+}
+
+// eventBuf builds an event payload. Its methods mirror
+// the runtime's TraceBuf and traceReader's decoding methods.
+type eventBuf struct {
+	buf []byte
+}
+
+func (eb *eventBuf) Byte(b byte) *eventBuf {
+	eb.buf = append(eb.buf, b)
+	return eb
+}
+
+func (eb *eventBuf) Bytes(b []byte) *eventBuf {
+	eb.buf = append(eb.buf, b...)
+	return eb
+}
+
+func (eb *eventBuf) String(s string) *eventBuf {
+	return eb.UVarint(uint64(len(s))).Bytes([]byte(s))
+}
+
+func (eb *eventBuf) ByteString(b []byte) *eventBuf {
+	return eb.UVarint(uint64(len(b))).Bytes(b)
+}
+
+func (eb *eventBuf) Uint32(x uint32) *eventBuf {
+	var b [4]byte
+	bin.PutUint32(b[:], x)
+	return eb.Bytes(b[:])
+}
+
+func (eb *eventBuf) Uint64(x uint64) *eventBuf {
+	var b [8]byte
+	bin.PutUint64(b[:], x)
+	return eb.Bytes(b[:])
+}
+
+func (eb *eventBuf) Varint(x int64) *eventBuf {
+	var u uint64
+	if x < 0 {
+		u = (^uint64(x) << 1) | 1 // complement x, bit 0 is 1
+	} else {
+		u = uint64(x) << 1 // do not complement x, bit 0 is 0
+	}
+	return eb.UVarint(u)
+}
+
+func (eb *eventBuf) UVarint(u uint64) *eventBuf {
+	var b [binary.MaxVarintLen64]byte
+	n := binary.PutUvarint(b[:], u)
+	return eb.Bytes(b[:n])
+}
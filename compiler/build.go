@@ -36,6 +36,10 @@ type Config struct {
 	// Debug specifies whether to compile in debug mode.
 	Debug bool
 
+	// AllowMethodOverride specifies whether the built app should honor
+	// the X-HTTP-Method-Override header on POST requests.
+	AllowMethodOverride bool
+
 	// EncoreRuntimePath if set, causes builds to introduce a temporary replace directive
 	// that replaces the module path to the "encore.dev" module.
 	// This lets us replace the implementation for building.
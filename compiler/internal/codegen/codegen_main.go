@@ -31,6 +31,10 @@ type decodeKey struct {
 }
 
 type Builder struct {
+	// AllowMethodOverride specifies whether the generated main package
+	// configures the runtime to honor the X-HTTP-Method-Override header.
+	AllowMethodOverride bool
+
 	res *parser.Result
 
 	builtins     []decoderDescriptor
@@ -105,11 +109,7 @@ func (b *Builder) Main() *File {
 		}),
 		Line(),
 
-		Id("cfg").Op(":=").Op("&").Qual("encore.dev/runtime/config", "ServerConfig").Values(Dict{
-			Id("Services"): Id("services"),
-			Id("Testing"):  False(),
-			Id("AuthData"): b.authDataType(),
-		}),
+		Id("cfg").Op(":=").Op("&").Qual("encore.dev/runtime/config", "ServerConfig").Values(b.serverConfig()),
 		Id("srv").Op("=").Qual("encore.dev/runtime", "Setup").Call(Id("cfg")),
 		Qual("encore.dev/storage/sqldb", "Setup").Call(Id("cfg")),
 		Id("srv").Dot("ListenAndServe").Call(),
@@ -127,6 +127,18 @@ func (b *Builder) Main() *File {
 	return f
 }
 
+func (b *Builder) serverConfig() Dict {
+	cfg := Dict{
+		Id("Services"): Id("services"),
+		Id("Testing"):  False(),
+		Id("AuthData"): b.authDataType(),
+	}
+	if b.AllowMethodOverride {
+		cfg[Id("AllowMethodOverride")] = True()
+	}
+	return cfg
+}
+
 func (b *Builder) buildRPC(f *File, svc *est.Service, rpc *est.RPC) *Statement {
 	return Func().Id("__encore_"+svc.Name+"_"+rpc.Name).Params(
 		Id("w").Qual("net/http", "ResponseWriter"),
@@ -98,6 +98,32 @@ func TestCodeGenMain(t *testing.T) {
 		})
 	}
 }
+
+func TestCodeGenMain_AllowMethodOverride(t *testing.T) {
+	c := qt.New(t)
+	archiveData, err := os.ReadFile("./testdata/empty.txt")
+	c.Assert(err, qt.IsNil)
+	base := c.TempDir()
+	err = txtar.Write(txtar.Parse(archiveData), base)
+	c.Assert(err, qt.IsNil)
+
+	res, err := parser.Parse(&parser.Config{
+		AppRoot:    base,
+		ModulePath: "encore.app",
+		WorkingDir: ".",
+	})
+	c.Assert(err, qt.IsNil)
+
+	for _, allow := range []bool{false, true} {
+		bld := NewBuilder(res)
+		bld.AllowMethodOverride = allow
+		var buf bytes.Buffer
+		err = bld.Main().Render(&buf)
+		c.Assert(err, qt.IsNil)
+		c.Assert(strings.Contains(buf.String(), "AllowMethodOverride: true"), qt.Equals, allow)
+	}
+}
+
 func TestCodeGen_TestMain(t *testing.T) {
 	c := qt.New(t)
 	tests, err := filepath.Glob("./testdata/*.txt")
@@ -0,0 +1,142 @@
+// Package router routes API requests to endpoint handlers.
+//
+// It implements the HTTP method handling of Encore apps on top of httprouter:
+// serving HEAD requests using GET handlers, answering OPTIONS requests
+// and requests with unsupported methods with the allowed methods,
+// and (optionally) method override via the X-HTTP-Method-Override header.
+package router
+
+import (
+	"net/http"
+	"sort"
+	"strings"
+
+	"github.com/julienschmidt/httprouter"
+)
+
+// wildcardMethod is an internal method name we register wildcard methods under.
+const wildcardMethod = "__ENCORE_WILDCARD__"
+
+// MethodOverrideHeader is the header clients can use to tunnel
+// other HTTP methods through POST, if enabled via AllowMethodOverride.
+const MethodOverrideHeader = "X-HTTP-Method-Override"
+
+// overridableMethods are the methods a POST request may be overridden to.
+// Safe methods are excluded since they would be served with the semantics
+// (and, for HEAD, the response body) of the original POST request.
+var overridableMethods = map[string]bool{
+	"PUT":    true,
+	"PATCH":  true,
+	"DELETE": true,
+}
+
+// Router routes requests to endpoint handlers.
+type Router struct {
+	// AllowMethodOverride specifies whether POST requests may specify
+	// the method to use (PUT, PATCH or DELETE) via MethodOverrideHeader.
+	AllowMethodOverride bool
+
+	router  *httprouter.Router
+	methods map[string]bool // HTTP methods with registered endpoints
+}
+
+// New returns a new, empty Router.
+func New() *Router {
+	return &Router{
+		router:  httprouter.New(),
+		methods: make(map[string]bool),
+	}
+}
+
+// Handle registers the handler h for the given path and methods.
+// The method "*" matches any method.
+func (r *Router) Handle(methods []string, path string, h httprouter.Handle) {
+	for _, m := range methods {
+		if m == "*" {
+			m = wildcardMethod
+		} else {
+			r.methods[m] = true
+		}
+		r.router.Handle(m, path, h)
+	}
+}
+
+// ServeHTTP implements http.Handler.
+func (r *Router) ServeHTTP(w http.ResponseWriter, req *http.Request) {
+	if r.AllowMethodOverride && req.Method == "POST" {
+		if m := strings.ToUpper(req.Header.Get(MethodOverrideHeader)); overridableMethods[m] {
+			req.Method = m
+			req.Header.Del(MethodOverrideHeader)
+		}
+	}
+
+	h, p, _ := r.router.Lookup(req.Method, req.URL.Path)
+	if h == nil {
+		h, p, _ = r.router.Lookup(wildcardMethod, req.URL.Path)
+	}
+	if h == nil && req.Method == "HEAD" {
+		// Serve HEAD requests using the GET handler, if there is one.
+		// The request is passed on as a GET so the handler decodes it the same way;
+		// net/http discards the response body since the original request is a HEAD.
+		if h, p, _ = r.router.Lookup("GET", req.URL.Path); h != nil {
+			getReq := *req
+			getReq.Method = "GET"
+			req = &getReq
+		}
+	}
+	if h == nil {
+		if allowed := r.allowedMethods(req.URL.Path); allowed != nil {
+			w.Header().Set("Allow", strings.Join(allowed, ", "))
+			if req.Method == "OPTIONS" {
+				w.WriteHeader(http.StatusNoContent)
+				return
+			}
+			w.Header().Set("Content-Type", "application/json")
+			w.WriteHeader(http.StatusMethodNotAllowed)
+			w.Write([]byte(`{
+  "code": "method_not_allowed",
+  "message": "method not allowed",
+  "details": null
+}
+`))
+			return
+		}
+
+		w.Header().Set("Content-Type", "application/json")
+		w.WriteHeader(404)
+		w.Write([]byte(`{
+  "code": "unknown_endpoint",
+  "message": "endpoint not found",
+  "details": null
+}
+`))
+		return
+	}
+	h(w, req, p)
+}
+
+// allowedMethods reports the methods with endpoints matching path,
+// for use in the Allow header. It reports nil if there are none.
+// Wildcard endpoints are not considered as they match any method.
+func (r *Router) allowedMethods(path string) []string {
+	var allowed []string
+	hasGet, hasHead := false, false
+	for m := range r.methods {
+		if h, _, _ := r.router.Lookup(m, path); h != nil {
+			allowed = append(allowed, m)
+			hasGet = hasGet || m == "GET"
+			hasHead = hasHead || m == "HEAD"
+		}
+	}
+	if len(allowed) == 0 {
+		return nil
+	}
+
+	// HEAD is served by GET handlers, and OPTIONS is handled by the router.
+	if hasGet && !hasHead {
+		allowed = append(allowed, "HEAD")
+	}
+	allowed = append(allowed, "OPTIONS")
+	sort.Strings(allowed)
+	return allowed
+}
@@ -0,0 +1,149 @@
+package router
+
+import (
+	"io/ioutil"
+	"net/http"
+	"net/http/httptest"
+	"strings"
+	"testing"
+
+	"github.com/julienschmidt/httprouter"
+)
+
+// newTestServer returns a test server with GET, POST and PUT endpoints
+// on the path /item. Each endpoint responds with its own method
+// in the X-Endpoint header and in the response body.
+func newTestServer(t *testing.T, allowMethodOverride bool) *httptest.Server {
+	r := New()
+	r.AllowMethodOverride = allowMethodOverride
+	for _, m := range []string{"GET", "POST", "PUT"} {
+		m := m
+		r.Handle([]string{m}, "/item", func(w http.ResponseWriter, req *http.Request, ps httprouter.Params) {
+			w.Header().Set("X-Endpoint", m)
+			w.Write([]byte(m))
+		})
+	}
+
+	ts := httptest.NewServer(r)
+	t.Cleanup(ts.Close)
+	return ts
+}
+
+// do makes a request to ts and returns the response along with its body.
+func do(t *testing.T, ts *httptest.Server, method, path string, header http.Header) (*http.Response, string) {
+	t.Helper()
+	req, err := http.NewRequest(method, ts.URL+path, nil)
+	if err != nil {
+		t.Fatal(err)
+	}
+	for k, v := range header {
+		req.Header[k] = v
+	}
+	resp, err := ts.Client().Do(req)
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer resp.Body.Close()
+	body, err := ioutil.ReadAll(resp.Body)
+	if err != nil {
+		t.Fatal(err)
+	}
+	return resp, string(body)
+}
+
+func TestRouter_Head(t *testing.T) {
+	ts := newTestServer(t, false)
+	getResp, getBody := do(t, ts, "GET", "/item", nil)
+	headResp, headBody := do(t, ts, "HEAD", "/item", nil)
+
+	if headResp.StatusCode != getResp.StatusCode {
+		t.Errorf("got status %d, want %d", headResp.StatusCode, getResp.StatusCode)
+	}
+	if got, want := headResp.Header.Get("X-Endpoint"), getResp.Header.Get("X-Endpoint"); got != want || got != "GET" {
+		t.Errorf("got X-Endpoint %q, want %q", got, want)
+	}
+	if getBody != "GET" {
+		t.Errorf("got GET body %q, want %q", getBody, "GET")
+	}
+	if headBody != "" {
+		t.Errorf("got HEAD body %q, want empty body", headBody)
+	}
+}
+
+func TestRouter_MethodOverride(t *testing.T) {
+	tests := []struct {
+		name     string
+		allow    bool
+		method   string
+		override string
+		want     string // endpoint handling the request
+	}{
+		{"disabled", false, "POST", "PUT", "POST"},
+		{"enabled", true, "POST", "PUT", "PUT"},
+		{"lowercase", true, "POST", "put", "PUT"},
+		{"no_header", true, "POST", "", "POST"},
+		{"not_post", true, "PUT", "POST", "PUT"},
+		{"get", true, "GET", "PUT", "GET"},
+		{"safe_method", true, "POST", "GET", "POST"},
+		{"head", true, "POST", "HEAD", "POST"},
+		{"unknown_method", true, "POST", "FOO", "POST"},
+	}
+	for _, test := range tests {
+		test := test
+		t.Run(test.name, func(t *testing.T) {
+			ts := newTestServer(t, test.allow)
+			header := make(http.Header)
+			if test.override != "" {
+				header.Set(MethodOverrideHeader, test.override)
+			}
+			resp, body := do(t, ts, test.method, "/item", header)
+			if resp.StatusCode != http.StatusOK {
+				t.Fatalf("got status %d, want %d", resp.StatusCode, http.StatusOK)
+			}
+			if got := resp.Header.Get("X-Endpoint"); got != test.want {
+				t.Errorf("got endpoint %q, want %q", got, test.want)
+			}
+			if body != test.want {
+				t.Errorf("got body %q, want %q", body, test.want)
+			}
+		})
+	}
+}
+
+func TestRouter_AllowedMethods(t *testing.T) {
+	ts := newTestServer(t, false)
+	const allow = "GET, HEAD, OPTIONS, POST, PUT"
+
+	resp, body := do(t, ts, "OPTIONS", "/item", nil)
+	if resp.StatusCode != http.StatusNoContent {
+		t.Errorf("OPTIONS: got status %d, want %d", resp.StatusCode, http.StatusNoContent)
+	}
+	if got := resp.Header.Get("Allow"); got != allow {
+		t.Errorf("OPTIONS: got Allow %q, want %q", got, allow)
+	}
+	if body != "" {
+		t.Errorf("OPTIONS: got body %q, want empty body", body)
+	}
+
+	resp, body = do(t, ts, "DELETE", "/item", nil)
+	if resp.StatusCode != http.StatusMethodNotAllowed {
+		t.Errorf("DELETE: got status %d, want %d", resp.StatusCode, http.StatusMethodNotAllowed)
+	}
+	if got := resp.Header.Get("Allow"); got != allow {
+		t.Errorf("DELETE: got Allow %q, want %q", got, allow)
+	}
+	if !strings.Contains(body, `"code": "method_not_allowed"`) {
+		t.Errorf("DELETE: got body %q, want method_not_allowed error", body)
+	}
+
+	resp, body = do(t, ts, "GET", "/unknown", nil)
+	if resp.StatusCode != http.StatusNotFound {
+		t.Errorf("GET /unknown: got status %d, want %d", resp.StatusCode, http.StatusNotFound)
+	}
+	if got := resp.Header.Get("Allow"); got != "" {
+		t.Errorf("GET /unknown: got Allow %q, want none", got)
+	}
+	if !strings.Contains(body, `"code": "unknown_endpoint"`) {
+		t.Errorf("GET /unknown: got body %q, want unknown_endpoint error", body)
+	}
+}
@@ -13,6 +13,13 @@ type ServerConfig struct {
 	Services []*Service
 	// AuthData is the custom auth data type, or "" if none
 	AuthData string
+
+	// AllowMethodOverride specifies whether POST requests may specify
+	// the HTTP method to use (PUT, PATCH or DELETE) via the
+	// X-HTTP-Method-Override header.
+	// It is disabled by default since it lets clients bypass
+	// method-based restrictions in proxies and firewalls.
+	AllowMethodOverride bool
 }
 
 type Service struct {
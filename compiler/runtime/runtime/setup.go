@@ -9,29 +9,21 @@ import (
 	"strconv"
 	"strings"
 
+	"encore.dev/internal/router"
 	"encore.dev/runtime/config"
 	"github.com/hashicorp/yamux"
-	"github.com/julienschmidt/httprouter"
 	"github.com/rs/zerolog"
 )
 
 type Server struct {
 	logger zerolog.Logger
-	router *httprouter.Router
+	router *router.Router
 }
 
-// wildcardMethod is an internal method name we register wildcard methods under.
-const wildcardMethod = "__ENCORE_WILDCARD__"
-
 func (srv *Server) handleRPC(service string, endpoint *config.Endpoint) {
 	logMsg := srv.logger.Info().Str("service", service).Str("endpoint", endpoint.Name).Str("path", endpoint.Path)
 	logMsg.Msg("registered endpoint")
-	for _, m := range endpoint.Methods {
-		if m == "*" {
-			m = wildcardMethod
-		}
-		srv.router.Handle(m, endpoint.Path, endpoint.Handler)
-	}
+	srv.router.Handle(endpoint.Methods, endpoint.Path, endpoint.Handler)
 }
 
 func (srv *Server) ListenAndServe() error {
@@ -44,7 +36,7 @@ func (srv *Server) ListenAndServe() error {
 		return err
 	}
 	httpsrv := &http.Server{
-		Handler: http.HandlerFunc(srv.handler),
+		Handler: srv.router,
 	}
 	return httpsrv.Serve(s)
 }
@@ -79,25 +71,6 @@ func (srv *Server) setupConn() (io.ReadWriteCloser, error) {
 	return rwc, nil
 }
 
-func (srv *Server) handler(w http.ResponseWriter, req *http.Request) {
-	h, p, _ := srv.router.Lookup(req.Method, req.URL.Path)
-	if h == nil {
-		h, p, _ = srv.router.Lookup(wildcardMethod, req.URL.Path)
-	}
-	if h == nil {
-		w.Header().Set("Content-Type", "application/json")
-		w.WriteHeader(404)
-		w.Write([]byte(`{
-  "code": "unknown_endpoint",
-  "message": "endpoint not found",
-  "details": null
-}
-`))
-		return
-	}
-	h(w, req, p)
-}
-
 func Setup(cfg *config.ServerConfig) *Server {
 	logger := zerolog.New(zerolog.ConsoleWriter{Out: os.Stderr}).With().Timestamp().Logger()
 	RootLogger = &logger
@@ -105,8 +78,9 @@ func Setup(cfg *config.ServerConfig) *Server {
 
 	srv := &Server{
 		logger: logger,
-		router: httprouter.New(),
+		router: router.New(),
 	}
+	srv.router.AllowMethodOverride = cfg.AllowMethodOverride
 	for _, svc := range cfg.Services {
 		for _, endpoint := range svc.Endpoints {
 			srv.handleRPC(svc.Name, endpoint)
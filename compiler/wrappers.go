@@ -30,6 +30,7 @@ func (b *builder) writeMainPkg() error {
 	b.addOverlay(filepath.Join(b.appRoot, mainPkgName, "main.go"), mainPath)
 
 	mb := codegen.NewBuilder(b.res)
+	mb.AllowMethodOverride = b.cfg.AllowMethodOverride
 	f := mb.Main()
 	return f.Render(file)
 }